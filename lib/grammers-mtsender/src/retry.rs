@@ -1,5 +1,11 @@
+use std::future::Future;
 use std::ops::ControlFlow;
-use std::time::Duration;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::Stream;
 
 /// a simple **Reconnection** Handler.
 ///
@@ -18,6 +24,20 @@ pub trait RetryPolicy: Send + Sync {
     fn should_retry(&self, attempts: usize) -> ControlFlow<(), Duration>;
 }
 
+/// extends a `RetryPolicy` with the ability to consult the error that triggered a retry, so a
+/// policy can distinguish a transient failure (e.g. a connection timeout) from a fatal one (e.g.
+/// an auth error) and fail fast on the latter instead of burning through its attempts.
+///
+/// the default implementation ignores `err` and forwards to `should_retry`, so every existing
+/// `RetryPolicy` gets this for free regardless of the error type `E`; `ConditionalRetry` is the
+/// one policy that implements this itself, for the one `E` its predicate cares about.
+pub trait RetryPolicyErr<E>: RetryPolicy {
+    fn should_retry_err(&self, attempts: usize, err: &E) -> ControlFlow<(), Duration> {
+        let _ = err;
+        self.should_retry(attempts)
+    }
+}
+
 /// the default implementation of the **ReconnectionPolicy**.
 pub struct NoRetry;
 
@@ -43,38 +63,409 @@ impl RetryPolicy for Fixed {
     }
 }
 
+impl<E> RetryPolicyErr<E> for Fixed {}
+
 impl RetryPolicy for NoRetry {
     fn should_retry(&self, _: usize) -> ControlFlow<(), Duration> {
         ControlFlow::Break(())
     }
 }
 
+impl<E> RetryPolicyErr<E> for NoRetry {}
+
+/// exponential-backoff implementation of the **ReconnectionPolicy** trait.
+///
+/// the delay for a given attempt is computed as `base * backoff_exponent^attempts`, capped at
+/// `max_delay`, and reconnection stops once `attempts` exceeds `max_retries`. enabling `jitter`
+/// randomizes the computed delay within `[0, computed]` (full jitter), which helps avoid a
+/// thundering herd of reconnections when many clients drop at once.
+pub struct ExponentialBackoff {
+    pub base: Duration,
+    pub backoff_exponent: f64,
+    pub max_delay: Duration,
+    pub max_retries: usize,
+    pub jitter: bool,
+}
+
+impl ExponentialBackoff {
+    pub const fn new(base: Duration, max_retries: usize) -> Self {
+        Self {
+            base,
+            backoff_exponent: 2.0,
+            max_delay: Duration::from_secs(60),
+            max_retries,
+            jitter: false,
+        }
+    }
+
+    pub const fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub const fn with_backoff_exponent(mut self, backoff_exponent: f64) -> Self {
+        self.backoff_exponent = backoff_exponent;
+        self
+    }
+
+    pub const fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub const fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+}
+
+impl RetryPolicy for ExponentialBackoff {
+    fn should_retry(&self, attempts: usize) -> ControlFlow<(), Duration> {
+        if attempts > self.max_retries {
+            return ControlFlow::Break(());
+        }
+
+        // do the power/multiply in f64 seconds space and clamp *before* converting back to a
+        // `Duration`: computing the uncapped `Duration` first can overflow and panic long before
+        // `max_delay` ever gets a chance to cap it.
+        let uncapped_secs = self.base.as_secs_f64() * self.backoff_exponent.powi(attempts as i32);
+        let secs = uncapped_secs.min(self.max_delay.as_secs_f64());
+        let delay = Duration::try_from_secs_f64(secs).unwrap_or(self.max_delay);
+
+        let delay = if self.jitter {
+            delay.mul_f64(rand::random::<f64>())
+        } else {
+            delay
+        };
+
+        ControlFlow::Continue(delay)
+    }
+}
+
+impl<E> RetryPolicyErr<E> for ExponentialBackoff {}
+
+/// wraps a `RetryPolicy` and only lets it decide when `predicate` accepts the triggering error;
+/// otherwise the connection attempt is given up on immediately (fails fast) regardless of the
+/// wrapped policy's own attempt budget.
+///
+/// this mirrors the common pattern of only retrying transient errors (e.g. connection resets or
+/// 5xx responses) while letting fatal errors (e.g. auth failures or 4xx responses) propagate
+/// right away.
+pub struct ConditionalRetry<P, F> {
+    pub policy: P,
+    pub predicate: F,
+}
+
+impl<P, F> ConditionalRetry<P, F> {
+    pub const fn new(policy: P, predicate: F) -> Self {
+        Self { policy, predicate }
+    }
+}
+
+impl<P, F> RetryPolicy for ConditionalRetry<P, F>
+where
+    P: RetryPolicy,
+    F: Send + Sync,
+{
+    fn should_retry(&self, attempts: usize) -> ControlFlow<(), Duration> {
+        self.policy.should_retry(attempts)
+    }
+}
+
+impl<P, F, E> RetryPolicyErr<E> for ConditionalRetry<P, F>
+where
+    P: RetryPolicyErr<E>,
+    F: Fn(&E) -> bool + Send + Sync,
+{
+    fn should_retry_err(&self, attempts: usize, err: &E) -> ControlFlow<(), Duration> {
+        if (self.predicate)(err) {
+            self.policy.should_retry_err(attempts, err)
+        } else {
+            ControlFlow::Break(())
+        }
+    }
+}
+
+struct TokenBucketState {
+    tokens: f64,
+    capacity: f64,
+    last_refill: Instant,
+}
+
+/// a shared token-bucket implementation of the **ReconnectionPolicy** trait.
+///
+/// unlike `Fixed` or `ExponentialBackoff`, which only bound the retries of a single operation,
+/// `TokenBucketRetry` is backed by a shared token balance: each retry attempt withdraws
+/// `withdraw_cost` tokens and is denied once the balance runs dry. tokens are replenished by
+/// `success_refill` whenever `record_success` is called, and refilled continuously over time at
+/// `refill_rate` tokens per second. because the state lives behind an `Arc<Mutex<_>>`, cloning a
+/// single instance across many retried operations enforces a global ceiling on retry load,
+/// independent of any one operation's own attempt-count logic.
+#[derive(Clone)]
+pub struct TokenBucketRetry {
+    state: Arc<Mutex<TokenBucketState>>,
+    withdraw_cost: f64,
+    success_refill: f64,
+    refill_rate: f64,
+    delay: Duration,
+}
+
+impl TokenBucketRetry {
+    pub fn new(capacity: f64, withdraw_cost: f64, success_refill: f64) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(TokenBucketState {
+                tokens: capacity,
+                capacity,
+                last_refill: Instant::now(),
+            })),
+            withdraw_cost,
+            success_refill,
+            refill_rate: 0.0,
+            delay: Duration::from_secs(1),
+        }
+    }
+
+    pub const fn with_refill_rate(mut self, tokens_per_sec: f64) -> Self {
+        self.refill_rate = tokens_per_sec;
+        self
+    }
+
+    pub const fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// replenishes the bucket by `success_refill` tokens.
+    ///
+    /// callers should invoke this once an operation guarded by this policy succeeds, so the
+    /// shared budget recovers once the server is healthy again.
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.tokens = (state.tokens + self.success_refill).min(state.capacity);
+    }
+
+    fn refill_elapsed(&self, state: &mut TokenBucketState) {
+        if self.refill_rate > 0.0 {
+            let elapsed = state.last_refill.elapsed().as_secs_f64();
+            state.tokens = (state.tokens + elapsed * self.refill_rate).min(state.capacity);
+            state.last_refill = Instant::now();
+        }
+    }
+}
+
+impl RetryPolicy for TokenBucketRetry {
+    fn should_retry(&self, _attempts: usize) -> ControlFlow<(), Duration> {
+        let mut state = self.state.lock().unwrap();
+        self.refill_elapsed(&mut state);
+
+        if state.tokens >= self.withdraw_cost {
+            state.tokens -= self.withdraw_cost;
+            ControlFlow::Continue(self.delay)
+        } else {
+            ControlFlow::Break(())
+        }
+    }
+}
+
+impl<E> RetryPolicyErr<E> for TokenBucketRetry {}
+
+/// lets the operation at the retry site classify its own outcome, instead of the macro inferring
+/// retryability solely from `Result::Err`.
+///
+/// `Success` breaks out of the retry loop with `Ok`, `Retry` consults the policy for a delay just
+/// like a plain error would, and `Fail` breaks immediately with the error regardless of the
+/// policy or how many attempts remain.
+pub enum RetryResult<T, E> {
+    Success(T),
+    Retry(E),
+    Fail(E),
+}
+
+impl<T, E> From<Result<T, E>> for RetryResult<T, E> {
+    fn from(res: Result<T, E>) -> Self {
+        match res {
+            Ok(value) => RetryResult::Success(value),
+            Err(err) => RetryResult::Retry(err),
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! retrying_result {
+    ($policy:expr, $body:expr) => {{
+        let mut attempts = 0;
+        loop {
+            let res: $crate::retry::RetryResult<_, _> = $body.into();
+            attempts += 1;
+            match res {
+                $crate::retry::RetryResult::Success(value) => break Ok(value),
+                $crate::retry::RetryResult::Fail(err) => break Err(err),
+                $crate::retry::RetryResult::Retry(err) => {
+                    match $crate::retry::RetryPolicyErr::should_retry_err(
+                        &$policy, attempts, &err,
+                    ) {
+                        std::ops::ControlFlow::Continue(timeout) => {
+                            tokio::time::sleep(timeout).await;
+                            continue;
+                        }
+                        std::ops::ControlFlow::Break(_) => break Err(err),
+                    }
+                }
+            }
+        }
+    }};
+}
+
 #[macro_export]
 macro_rules! retrying {
+    ($policy:expr, $body:expr) => {
+        $crate::retrying_result!($policy, $crate::retry::RetryResult::from($body))
+    };
+}
+
+/// the error returned when a retry loop gives up.
+///
+/// carries the final `error`, the number of `tries` that were made, and the `total_delay` spent
+/// sleeping between attempts, so callers have something actionable to log or emit as metrics
+/// instead of just the bare final error.
+#[derive(Debug)]
+pub struct RetryError<E> {
+    pub error: E,
+    pub tries: usize,
+    pub total_delay: Duration,
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for RetryError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "gave up after {} attempt(s) ({:?} spent retrying): {}",
+            self.tries, self.total_delay, self.error
+        )
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for RetryError<E> {}
+
+#[macro_export]
+macro_rules! retrying_detailed {
     ($policy:expr, $body:expr) => {{
         let mut attempts = 0;
+        let mut total_delay = std::time::Duration::ZERO;
         loop {
-            let res = $body;
+            let res: $crate::retry::RetryResult<_, _> = $body.into();
             attempts += 1;
             match res {
-                Ok(value) => {
-                    break Ok(value);
+                $crate::retry::RetryResult::Success(value) => break Ok(value),
+                $crate::retry::RetryResult::Fail(error) => {
+                    break Err($crate::retry::RetryError {
+                        error,
+                        tries: attempts,
+                        total_delay,
+                    });
                 }
-                Err(_) => match $policy.should_retry(attempts) {
-                    std::ops::ControlFlow::Continue(timeout) => {
-                        tokio::time::sleep(timeout).await;
-                        continue;
+                $crate::retry::RetryResult::Retry(error) => {
+                    match $policy.should_retry_err(attempts, &error) {
+                        std::ops::ControlFlow::Continue(timeout) => {
+                            total_delay += timeout;
+                            tokio::time::sleep(timeout).await;
+                            continue;
+                        }
+                        std::ops::ControlFlow::Break(_) => {
+                            break Err($crate::retry::RetryError {
+                                error,
+                                tries: attempts,
+                                total_delay,
+                            });
+                        }
                     }
-                    std::ops::ControlFlow::Break(_) => break res,
-                },
+                }
             }
         }
     }};
 }
 
+/// wraps a `Stream` of `Result<T, E>` and recovers from transient `Err` items instead of ending
+/// the stream outright.
+///
+/// on an `Err` item the wrapped `RetryPolicy` is consulted: if it allows another attempt, the
+/// adapter sleeps for the returned delay and resumes polling the *same* underlying stream,
+/// resetting its attempt counter on every item that succeeds. once the policy returns
+/// `ControlFlow::Break`, that error is yielded as the final item and the stream then ends. this
+/// lets long-lived loops (e.g. a client's update/accept loop) reuse the same policy abstraction
+/// that one-shot operations use via `retrying!`, instead of tearing down and recreating the whole
+/// stream on every hiccup.
+pub struct RetryStream<S, P> {
+    stream: S,
+    policy: P,
+    attempts: usize,
+    sleep: Option<Pin<Box<tokio::time::Sleep>>>,
+    done: bool,
+}
+
+impl<S, P> RetryStream<S, P> {
+    pub fn new(stream: S, policy: P) -> Self {
+        Self {
+            stream,
+            policy,
+            attempts: 0,
+            sleep: None,
+            done: false,
+        }
+    }
+}
+
+impl<S, P, T, E> Stream for RetryStream<S, P>
+where
+    S: Stream<Item = Result<T, E>> + Unpin,
+    P: RetryPolicyErr<E> + Unpin,
+{
+    type Item = Result<T, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            if let Some(sleep) = this.sleep.as_mut() {
+                match sleep.as_mut().poll(cx) {
+                    Poll::Ready(()) => this.sleep = None,
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+
+            match Pin::new(&mut this.stream).poll_next(cx) {
+                Poll::Ready(Some(Ok(value))) => {
+                    this.attempts = 0;
+                    return Poll::Ready(Some(Ok(value)));
+                }
+                Poll::Ready(Some(Err(err))) => {
+                    this.attempts += 1;
+                    match this.policy.should_retry_err(this.attempts, &err) {
+                        ControlFlow::Continue(delay) => {
+                            this.sleep = Some(Box::pin(tokio::time::sleep(delay)));
+                            continue;
+                        }
+                        ControlFlow::Break(()) => {
+                            this.done = true;
+                            return Poll::Ready(Some(Err(err)));
+                        }
+                    }
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use futures::StreamExt;
 
     struct Erroring {
         cnt: usize,
@@ -111,4 +502,171 @@ mod tests {
         let r = retrying!(policy, err.run().await);
         assert!(r.is_err());
     }
+
+    #[test]
+    fn test_exponential_backoff_grows_and_caps() {
+        let policy = ExponentialBackoff::new(Duration::from_millis(100), 10)
+            .with_backoff_exponent(2.0)
+            .with_max_delay(Duration::from_millis(300));
+
+        assert_eq!(
+            policy.should_retry(0),
+            ControlFlow::Continue(Duration::from_millis(100))
+        );
+        assert_eq!(
+            policy.should_retry(1),
+            ControlFlow::Continue(Duration::from_millis(200))
+        );
+        // would be 400ms uncapped, but max_delay caps it at 300ms.
+        assert_eq!(
+            policy.should_retry(2),
+            ControlFlow::Continue(Duration::from_millis(300))
+        );
+    }
+
+    #[test]
+    fn test_exponential_backoff_max_retries() {
+        let policy = ExponentialBackoff::new(Duration::from_millis(100), 3);
+
+        assert!(policy.should_retry(3).is_continue());
+        assert_eq!(policy.should_retry(4), ControlFlow::Break(()));
+    }
+
+    #[test]
+    fn test_exponential_backoff_jitter_stays_in_range() {
+        let policy = ExponentialBackoff::new(Duration::from_millis(100), 10).with_jitter(true);
+
+        match policy.should_retry(1) {
+            ControlFlow::Continue(delay) => assert!(delay <= Duration::from_millis(200)),
+            ControlFlow::Break(_) => panic!("expected to retry"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_conditional_retry_fails_fast_on_rejected_error() {
+        let policy = ConditionalRetry::new(Fixed::new(10, Duration::new(0, 0)), |err: &&str| {
+            *err != "fatal"
+        });
+        let mut attempts = 0;
+
+        let r: Result<(), &str> = retrying!(policy, {
+            attempts += 1;
+            Err("fatal")
+        });
+
+        assert!(r.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_conditional_retry_retries_accepted_error() {
+        let policy =
+            ConditionalRetry::new(Fixed::new(10, Duration::new(0, 0)), |_err: &usize| true);
+        let mut err = Erroring::new(5);
+
+        let r = retrying!(policy, err.run().await);
+        assert!(r.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_retrying_result_macro_success() {
+        let policy = Fixed::new(10, Duration::new(0, 0));
+
+        let r: Result<usize, usize> =
+            retrying_result!(policy, RetryResult::<usize, usize>::Success(42));
+        assert_eq!(r, Ok(42));
+    }
+
+    #[tokio::test]
+    async fn test_retrying_result_macro_retries_then_succeeds() {
+        let policy = Fixed::new(10, Duration::new(0, 0));
+        let mut err = Erroring::new(5);
+
+        let r = retrying_result!(policy, RetryResult::from(err.run().await));
+        assert!(r.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_retrying_result_macro_fails_fast() {
+        let policy = Fixed::new(10, Duration::new(0, 0));
+        let mut attempts = 0;
+
+        let r: Result<(), &str> = retrying_result!(policy, {
+            attempts += 1;
+            RetryResult::Fail("fatal")
+        });
+
+        assert_eq!(r, Err("fatal"));
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn test_token_bucket_denies_once_exhausted() {
+        let policy = TokenBucketRetry::new(2.0, 1.0, 0.0);
+
+        assert!(policy.should_retry(0).is_continue());
+        assert!(policy.should_retry(0).is_continue());
+        assert_eq!(policy.should_retry(0), ControlFlow::Break(()));
+    }
+
+    #[test]
+    fn test_token_bucket_shared_across_clones() {
+        let policy = TokenBucketRetry::new(1.0, 1.0, 0.0);
+        let cloned = policy.clone();
+
+        assert!(policy.should_retry(0).is_continue());
+        // the clone shares the same underlying balance, so it sees the withdrawal above.
+        assert_eq!(cloned.should_retry(0), ControlFlow::Break(()));
+    }
+
+    #[test]
+    fn test_token_bucket_record_success_replenishes() {
+        let policy = TokenBucketRetry::new(1.0, 1.0, 1.0);
+
+        assert!(policy.should_retry(0).is_continue());
+        assert_eq!(policy.should_retry(0), ControlFlow::Break(()));
+
+        policy.record_success();
+        assert!(policy.should_retry(0).is_continue());
+    }
+
+    #[tokio::test]
+    async fn test_retrying_detailed_reports_tries_and_total_delay() {
+        let policy = Fixed::new(3, Duration::from_millis(10));
+        let mut err = Erroring::new(5);
+
+        let r = retrying_detailed!(policy, err.run().await);
+        let err = r.unwrap_err();
+
+        assert_eq!(err.tries, 4);
+        assert_eq!(err.total_delay, Duration::from_millis(30));
+    }
+
+    #[tokio::test]
+    async fn test_retrying_detailed_succeeds() {
+        let policy = Fixed::new(10, Duration::new(0, 0));
+        let mut err = Erroring::new(5);
+
+        let r = retrying_detailed!(policy, err.run().await);
+        assert!(r.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_retry_stream_recovers_from_transient_error() {
+        let source = futures::stream::iter([Err(1), Ok(2), Err(3), Ok(4)]);
+        let mut stream = RetryStream::new(source, Fixed::new(10, Duration::new(0, 0)));
+
+        assert_eq!(stream.next().await, Some(Ok(2)));
+        assert_eq!(stream.next().await, Some(Ok(4)));
+        assert_eq!(stream.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_retry_stream_ends_once_policy_breaks() {
+        let source = futures::stream::iter([Err(1), Err(2), Err(3), Ok(4)]);
+        let mut stream = RetryStream::new(source, Fixed::new(1, Duration::new(0, 0)));
+
+        assert_eq!(stream.next().await, Some(Err(2)));
+        assert_eq!(stream.next().await, None);
+    }
 }